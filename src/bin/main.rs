@@ -5,79 +5,70 @@ use futures::stream::FuturesUnordered;
 use futures::stream::StreamExt;
 use octocrab::OctocrabBuilder;
 
-use crab_log::{get_commits, pr_from_commit, Config, PRKind};
+use crab_log::{get_commits, pr_from_commit, render, Args, Config, Error, PRKind};
 
 #[tokio::main]
-async fn main() -> Result<(), ()> {
-    // TODO: use *owner* and *repo* to read the config from HEAD on GH
-    // config: who to ignore + labels
+async fn main() -> Result<(), Error> {
     let token = std::env::var("GITHUB_TOKEN").expect("Missing GITHUB_TOKEN env var");
-    let config = Config::parse();
+    let config = Config::from_args(Args::parse())?;
     let crab = OctocrabBuilder::new()
         .personal_token(token)
         .build()
         .expect("TODO, die gracefuly");
 
-    let date_last_release = "2022-06-11T00:00:00"; // TODO: should get date of last release magically
+    let date_last_release = config.since_date(&crab).await?;
+    let until = config.until_date(&crab).await?;
     eprintln!("Fetching all commits since last release");
-    let commits = get_commits(&config, date_last_release, &crab).await?;
+    let commits = get_commits(&config, &date_last_release, until, &crab).await?;
     eprintln!("commits:      {:3}", commits.len());
 
     let mut futs: FuturesUnordered<_> = commits
         .into_iter()
-        // TODO: make this configurable
-        // disregard commits by dependabot
-        .filter(|com| !com.author.contains("dependabot"))
+        .filter(|com| {
+            !config
+                .ignored_authors
+                .iter()
+                .any(|ignored| com.author.contains(ignored.as_str()))
+        })
         .map(|commit| pr_from_commit(commit, &crab))
         .collect();
     eprintln!("user commits: {:3}", futs.len());
 
-    let mut features = BinaryHeap::new();
-    let mut fixes = BinaryHeap::new();
-    let mut improvements = BinaryHeap::new();
+    let mut categorized: Vec<BinaryHeap<_>> = config
+        .categories
+        .iter()
+        .map(|_| BinaryHeap::new())
+        .collect();
     let mut ignored = BinaryHeap::new();
     while let Some(re) = futs.next().await {
         // put PR in appropriate list
         if let Ok(pr) = re {
             match pr.kind {
-                PRKind::Feature => {
-                    features.push(pr);
-                }
-                PRKind::BugFix => {
-                    fixes.push(pr);
-                }
-                PRKind::Internal => {
-                    improvements.push(pr);
-                }
-                PRKind::Ignored => {
-                    ignored.push(pr);
-                }
+                PRKind::Category(idx) => categorized[idx].push(pr),
+                PRKind::Ignored => ignored.push(pr),
             }
         }
     }
-    eprintln!("features:     {:3}", features.len());
-    eprintln!("fixes:        {:3}", fixes.len());
-    eprintln!("improvements: {:3}", improvements.len());
-    eprintln!("ignored:      {:3}", ignored.len());
-
-    println!("### Feature Enhancements");
-    println!();
-    for feat in features.into_sorted_vec().iter() {
-        println!("- {}", feat.commit);
+    for (category, prs) in config.categories.iter().zip(&categorized) {
+        eprintln!("{}: {:3}", category.heading, prs.len());
     }
-    println!();
+    eprintln!("ignored:      {:3}", ignored.len());
 
-    println!("### Bug Fixes");
-    println!();
-    for fix in fixes.into_sorted_vec().iter() {
-        println!("- {}", fix.commit);
+    match config.current_version() {
+        Ok(Some(current_version)) => {
+            let next_version = match crab_log::recommend_bump(categorized.iter().flatten()) {
+                Some(level) => current_version.bump(level),
+                None => current_version,
+            };
+            eprintln!("Recommended next release: {next_version}");
+        }
+        Ok(None) => {}
+        Err(err) => eprintln!("warning: couldn't determine the current version: {err}"),
     }
-    println!();
 
-    println!("### Internal Improvements");
-    println!();
-    for improvement in improvements.into_sorted_vec().iter() {
-        println!("- {}", improvement.commit);
+    render(&config, config.format(), categorized);
+    if config.cache.save().is_err() {
+        eprintln!("warning: failed to write the cache back to disk");
     }
     Ok(())
 }
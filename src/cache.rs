@@ -0,0 +1,248 @@
+//! A disk-backed cache for GraphQL responses, so re-running crab-log shortly
+//! after a previous run doesn't re-fetch PR labels that haven't changed.
+//!
+//! History pages get the same treatment, but only within a TTL window: the
+//! first (most recent) page's cache key is bucketed to the TTL since its
+//! `until` is "now" and would otherwise be unique per run. Re-running after
+//! the TTL has elapsed, or with a `--to` tag pinned to a different instant,
+//! still misses.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+/// The commit fields `Config::build_commit` needs, fetched live or read back
+/// out of a cached history page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitFields {
+    pub author: Option<String>,
+    pub message: String,
+    pub authored_date: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry<T> {
+    cached_at_secs: u64,
+    value: T,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    /// Keyed by PR number.
+    #[serde(default)]
+    labels: HashMap<String, CacheEntry<Vec<String>>>,
+    /// Keyed by `"{since}|{until}"`.
+    #[serde(default)]
+    history_pages: HashMap<String, CacheEntry<Vec<CommitFields>>>,
+}
+
+/// A disk-backed cache of GraphQL responses for one `(owner, repo)`.
+#[derive(Debug)]
+pub struct Cache {
+    path: Option<PathBuf>,
+    ttl: Duration,
+    read_enabled: bool,
+    write_enabled: bool,
+    data: Mutex<CacheFile>,
+}
+
+impl Cache {
+    /// Opens (or creates) the cache file for `owner/repo` under `cache_dir`.
+    ///
+    /// `no_cache` disables both reading and writing; `refresh` disables
+    /// reading but still writes, so a run can force a refetch while still
+    /// updating what's on disk for next time.
+    pub fn open(
+        cache_dir: Option<&Path>,
+        owner: &str,
+        repo: &str,
+        ttl: Duration,
+        no_cache: bool,
+        refresh: bool,
+    ) -> Self {
+        let path = cache_dir.map(|dir| dir.join(format!("{owner}-{repo}.json")));
+        let data = if no_cache {
+            CacheFile::default()
+        } else {
+            path.as_deref()
+                .and_then(|path| std::fs::read_to_string(path).ok())
+                .and_then(|contents| serde_json::from_str(&contents).ok())
+                .unwrap_or_default()
+        };
+        Cache {
+            path,
+            ttl,
+            read_enabled: !no_cache && !refresh,
+            write_enabled: !no_cache,
+            data: Mutex::new(data),
+        }
+    }
+
+    fn is_fresh(&self, cached_at_secs: u64) -> bool {
+        now_unix().saturating_sub(cached_at_secs) < self.ttl.as_secs()
+    }
+
+    /// Rounds a Unix timestamp down to the start of its TTL-sized bucket.
+    ///
+    /// The history-page cache is keyed in part by the range's `until`
+    /// timestamp, which absent `--to` is "now" and so is different on every
+    /// invocation — bucketing it means runs that land in the same TTL
+    /// window still share a cache key instead of each missing the cache.
+    pub fn bucket(&self, unix_secs: u64) -> u64 {
+        let ttl = self.ttl.as_secs().max(1);
+        (unix_secs / ttl) * ttl
+    }
+
+    pub fn get_labels(&self, pr_number: &str) -> Option<Vec<String>> {
+        if !self.read_enabled {
+            return None;
+        }
+        let data = self.data.lock().unwrap();
+        let entry = data.labels.get(pr_number)?;
+        self.is_fresh(entry.cached_at_secs)
+            .then(|| entry.value.clone())
+    }
+
+    pub fn put_labels(&self, pr_number: &str, labels: Vec<String>) {
+        if !self.write_enabled {
+            return;
+        }
+        self.data.lock().unwrap().labels.insert(
+            pr_number.to_string(),
+            CacheEntry {
+                cached_at_secs: now_unix(),
+                value: labels,
+            },
+        );
+    }
+
+    pub fn get_history_page(&self, since: &str, until: &str) -> Option<Vec<CommitFields>> {
+        if !self.read_enabled {
+            return None;
+        }
+        let data = self.data.lock().unwrap();
+        let entry = data.history_pages.get(&history_key(since, until))?;
+        self.is_fresh(entry.cached_at_secs)
+            .then(|| entry.value.clone())
+    }
+
+    pub fn put_history_page(&self, since: &str, until: &str, commits: Vec<CommitFields>) {
+        if !self.write_enabled {
+            return;
+        }
+        self.data.lock().unwrap().history_pages.insert(
+            history_key(since, until),
+            CacheEntry {
+                cached_at_secs: now_unix(),
+                value: commits,
+            },
+        );
+    }
+
+    /// Writes the cache back to disk, if caching is enabled and a
+    /// `--cache-dir` was given.
+    pub fn save(&self) -> Result<(), ()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !self.write_enabled {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|_| ())?;
+        }
+        let json = serde_json::to_string_pretty(&*self.data.lock().unwrap()).map_err(|_| ())?;
+        std::fs::write(path, json).map_err(|_| ())
+    }
+}
+
+fn history_key(since: &str, until: &str) -> String {
+    format!("{since}|{until}")
+}
+
+fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(no_cache: bool, refresh: bool) -> Cache {
+        Cache::open(
+            None,
+            "owner",
+            "repo",
+            Duration::from_secs(60),
+            no_cache,
+            refresh,
+        )
+    }
+
+    #[test]
+    fn bucket_rounds_down_to_ttl_multiple() {
+        let c = cache(false, false);
+        assert_eq!(c.bucket(125), 120);
+    }
+
+    #[test]
+    fn bucket_is_stable_within_the_same_window() {
+        let c = cache(false, false);
+        assert_eq!(c.bucket(121), c.bucket(179));
+    }
+
+    #[test]
+    fn is_fresh_within_ttl() {
+        let c = cache(false, false);
+        assert!(c.is_fresh(now_unix() - 30));
+    }
+
+    #[test]
+    fn is_fresh_boundary_is_stale() {
+        let c = cache(false, false);
+        assert!(!c.is_fresh(now_unix() - 60));
+    }
+
+    #[test]
+    fn is_fresh_past_ttl_is_stale() {
+        let c = cache(false, false);
+        assert!(!c.is_fresh(now_unix() - 61));
+    }
+
+    #[test]
+    fn default_cache_reads_and_writes() {
+        let c = cache(false, false);
+        c.put_labels("1", vec!["bug".to_string()]);
+        assert_eq!(c.get_labels("1"), Some(vec!["bug".to_string()]));
+    }
+
+    #[test]
+    fn no_cache_disables_reads_and_writes() {
+        let c = cache(true, false);
+        c.put_labels("1", vec!["bug".to_string()]);
+        assert_eq!(c.get_labels("1"), None);
+        assert!(c.data.lock().unwrap().labels.is_empty());
+    }
+
+    #[test]
+    fn refresh_writes_but_does_not_read() {
+        let c = cache(false, true);
+        c.put_labels("1", vec!["bug".to_string()]);
+        assert_eq!(c.get_labels("1"), None);
+        assert!(c.data.lock().unwrap().labels.contains_key("1"));
+    }
+
+    #[test]
+    fn history_key_joins_since_and_until() {
+        assert_eq!(
+            history_key("2024-01-01", "2024-02-01"),
+            "2024-01-01|2024-02-01"
+        );
+    }
+}
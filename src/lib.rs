@@ -1,29 +1,170 @@
-use std::collections::{HashMap, BinaryHeap};
+mod cache;
+
+use std::collections::BinaryHeap;
 use std::fmt::Display;
+use std::path::Path;
 
-use chrono::{DateTime, Duration, FixedOffset, Utc};
-use clap::Parser;
+use chrono::{DateTime as ChronoDateTime, Duration, FixedOffset, Utc};
+use clap::{Parser, ValueEnum};
+use graphql_client::{GraphQLQuery, Response};
 use octocrab::Octocrab;
+use serde::Deserialize;
 use serde_json::Value;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+use cache::{Cache, CommitFields};
+
+// graphql-client maps custom GraphQL scalars to whatever Rust type of the
+// same name is in scope; our schema only ever sends these over the wire as
+// ISO-8601 strings. `chrono::DateTime` is imported above under a different
+// name so it doesn't shadow this.
+type DateTime = String;
+type GitTimestamp = String;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/get_commits.graphql",
+    response_derives = "Debug, Clone"
+)]
+struct GetCommits;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/pr_labels.graphql",
+    response_derives = "Debug, Clone"
+)]
+struct PrLabels;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/last_release.graphql",
+    response_derives = "Debug, Clone"
+)]
+struct LastRelease;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "graphql/schema.graphql",
+    query_path = "graphql/tag_commit_date.graphql",
+    response_derives = "Debug, Clone"
+)]
+struct TagCommitDate;
+
+/// One commit out of a page of `GetCommits::history`.
+type CommitNode = get_commits::GetCommitsRepositoryRefsEdgesNodeTargetOnCommitHistoryEdgesNode;
+/// The `main` branch's tip, as resolved by `GetCommits::refs`.
+type RefTarget = get_commits::GetCommitsRepositoryRefsEdgesNodeTarget;
+/// The commit a `--from`/`--to` tag points at, as resolved by `TagCommitDate::ref`.
+type TagRefTarget = tag_commit_date::TagCommitDateRepositoryGitRefTarget;
+/// What an annotated tag's own `target` resolves to — always a commit, since
+/// GitHub doesn't let a tag point at another tag.
+type TagTarget = tag_commit_date::TagCommitDateRepositoryGitRefTargetOnTagTarget;
+
+impl From<&CommitNode> for CommitFields {
+    fn from(node: &CommitNode) -> Self {
+        CommitFields {
+            author: node
+                .author
+                .as_ref()
+                .and_then(|actor| actor.user.as_ref())
+                .map(|user| user.login.clone()),
+            message: node.message.clone(),
+            authored_date: node.authored_date.clone(),
+        }
+    }
+}
+
+/// Errors talking to the GitHub GraphQL API.
+#[derive(Debug)]
+pub enum Error {
+    /// The request itself (transport, auth, JSON decoding) failed.
+    Request(Box<octocrab::Error>),
+    /// The server accepted the request but returned GraphQL errors.
+    GraphQl(Vec<graphql_client::Error>),
+    /// The response had neither errors nor the data we expected.
+    MissingData(&'static str),
+    /// The CLI args and `crab-log.toml` together didn't provide enough to run.
+    Config(&'static str),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Request(err) => write!(f, "request failed: {err}"),
+            Error::GraphQl(errors) => write!(f, "GraphQL errors: {errors:?}"),
+            Error::MissingData(what) => write!(f, "response was missing {what}"),
+            Error::Config(what) => write!(f, "configuration error: {what}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+async fn run_query<Q: GraphQLQuery>(
+    crab: &Octocrab,
+    variables: Q::Variables,
+) -> Result<Q::ResponseData, Error> {
+    let body = Q::build_query(variables);
+    let response: Response<Q::ResponseData> = crab
+        .graphql(&body)
+        .await
+        .map_err(|err| Error::Request(Box::new(err)))?;
+    if let Some(errors) = response.errors.filter(|errors| !errors.is_empty()) {
+        return Err(Error::GraphQl(errors));
+    }
+    response.data.ok_or(Error::MissingData("data"))
+}
+
+#[derive(Debug, Clone)]
 pub struct Commit<'c> {
     pub config: &'c Config,
     pub author: String,
     pub message: String,
     pub pr_number: String,
-    pub date: DateTime<FixedOffset>,
+    pub date: ChronoDateTime<FixedOffset>,
+    /// The Conventional-Commits type (`feat`, `fix`, ...), if the message has one.
+    pub conventional_type: Option<String>,
+    /// Whether the commit marks a breaking change, either via a `!` before the
+    /// `:` or a `BREAKING CHANGE` token anywhere in the body.
+    pub breaking: bool,
 }
 
-impl<'c> Display for Commit<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let host = "https://github.com";
+// `Config` can't derive `PartialEq` itself (its cache holds a `Mutex`), so
+// equality here ignores the shared `config` backreference and compares the
+// commit's own data instead.
+impl PartialEq for Commit<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.author == other.author
+            && self.message == other.message
+            && self.pr_number == other.pr_number
+            && self.date == other.date
+            && self.conventional_type == other.conventional_type
+            && self.breaking == other.breaking
+    }
+}
+
+impl Eq for Commit<'_> {}
+
+impl Commit<'_> {
+    /// The GitHub URL of the PR this commit belongs to.
+    pub fn pr_url(&self) -> String {
         let owner = &self.config.owner;
         let repo = &self.config.repo;
+        format!("https://github.com/{owner}/{repo}/pull/{}", &self.pr_number)
+    }
+}
+
+impl Display for Commit<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{} by @{} in [#{}]({}/{}/{}/pull/{})",
-            &self.message, &self.author, &self.pr_number, host, owner, repo, &self.pr_number
+            "{} by @{} in [#{}]({})",
+            &self.message,
+            &self.author,
+            &self.pr_number,
+            self.pr_url()
         )
     }
 }
@@ -40,189 +181,521 @@ impl PartialOrd for Commit<'_> {
     }
 }
 
-#[derive(Parser, Debug, PartialEq, Eq)]
+/// Raw command-line arguments, before merging in `crab-log.toml` defaults.
+#[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
-pub struct Config {
+pub struct Args {
     #[arg(short, long)]
-    owner: String,
+    owner: Option<String>,
 
     #[arg(short, long)]
-    repo: String,
+    repo: Option<String>,
+
+    /// Path to a `crab-log.toml` declaring categories, ignored authors, and repo defaults.
+    #[arg(long)]
+    config: Option<std::path::PathBuf>,
+
+    /// The version to bump from, e.g. `1.2.3`. Takes precedence over `--manifest-path`.
+    #[arg(long)]
+    current_version: Option<String>,
+
+    /// Path to a `Cargo.toml` or `package.json` to read the current version from.
+    #[arg(long)]
+    manifest_path: Option<std::path::PathBuf>,
+
+    /// The format to render the changelog in.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Markdown)]
+    format: OutputFormat,
+
+    /// Directory to cache GraphQL responses in. Caching is disabled unless this is set.
+    #[arg(long)]
+    cache_dir: Option<std::path::PathBuf>,
+
+    /// How long a cached entry stays valid, in seconds.
+    #[arg(long, default_value_t = 3600)]
+    cache_ttl: u64,
+
+    /// Don't read or write the on-disk cache for this run.
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Re-fetch everything, but still update the on-disk cache with the results.
+    #[arg(long)]
+    refresh: bool,
+
+    /// Git tag to start the changelog from. Defaults to the latest GitHub release.
+    #[arg(long)]
+    from: Option<String>,
+
+    /// Git tag to end the changelog at. Defaults to now.
+    #[arg(long)]
+    to: Option<String>,
+}
+
+/// The output format `render` emits the changelog in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Markdown,
+    Rss,
+    Json,
+}
+
+/// A named changelog category: an ordered section heading, and the GitHub
+/// labels that route a PR into it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Category {
+    pub heading: String,
+    pub labels: Vec<String>,
+    /// Whether a PR routed into this category should count as at least a
+    /// minor bump for `recommend_bump`, the same as a `feat:` commit does.
+    #[serde(default)]
+    pub minor_bump: bool,
+}
+
+/// The contents of a `crab-log.toml`: categories, ignored authors, and
+/// fallback repo coordinates.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CrabLogConfig {
+    pub categories: Vec<Category>,
+    #[serde(default)]
+    pub ignored_authors: Vec<String>,
+    #[serde(default)]
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub repo: Option<String>,
+}
+
+impl CrabLogConfig {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|_| Error::Config("failed to read --config file"))?;
+        toml::from_str(&contents)
+            .map_err(|_| Error::Config("failed to parse --config file as TOML"))
+    }
+}
+
+impl Default for CrabLogConfig {
+    fn default() -> Self {
+        CrabLogConfig {
+            categories: vec![
+                Category {
+                    heading: "### Feature Enhancements".to_string(),
+                    labels: vec!["enhancement".to_string()],
+                    minor_bump: true,
+                },
+                Category {
+                    heading: "### Bug Fixes".to_string(),
+                    labels: vec!["bug".to_string()],
+                    minor_bump: false,
+                },
+                Category {
+                    heading: "### Internal Improvements".to_string(),
+                    labels: vec!["Internal".to_string()],
+                    minor_bump: false,
+                },
+            ],
+            ignored_authors: vec!["dependabot".to_string()],
+            owner: None,
+            repo: None,
+        }
+    }
+}
+
+/// The resolved configuration a changelog run uses, merging `Args` with
+/// whatever `crab-log.toml` declared.
+#[derive(Debug)]
+pub struct Config {
+    pub owner: String,
+    pub repo: String,
+    pub categories: Vec<Category>,
+    pub ignored_authors: Vec<String>,
+    current_version: Option<String>,
+    manifest_path: Option<std::path::PathBuf>,
+    format: OutputFormat,
+    pub cache: Cache,
+    from: Option<String>,
+    to: Option<String>,
 }
 
 impl Config {
-    pub fn build_commit(&self, value: &Value) -> Result<Commit, ()> {
-        fn get_pr_number(message: &str) -> Result<String, ()> {
+    /// Merges CLI args with the `crab-log.toml` pointed to by `--config`
+    /// (or the built-in defaults, if no config file was given).
+    pub fn from_args(args: Args) -> Result<Self, Error> {
+        let file_config = match &args.config {
+            Some(path) => CrabLogConfig::load(path)?,
+            None => CrabLogConfig::default(),
+        };
+        let owner = args.owner.or(file_config.owner).ok_or(Error::Config(
+            "missing --owner, and no crab-log.toml provided one",
+        ))?;
+        let repo = args.repo.or(file_config.repo).ok_or(Error::Config(
+            "missing --repo, and no crab-log.toml provided one",
+        ))?;
+        let cache = Cache::open(
+            args.cache_dir.as_deref(),
+            &owner,
+            &repo,
+            std::time::Duration::from_secs(args.cache_ttl),
+            args.no_cache,
+            args.refresh,
+        );
+        Ok(Config {
+            owner,
+            repo,
+            categories: file_config.categories,
+            ignored_authors: file_config.ignored_authors,
+            current_version: args.current_version,
+            manifest_path: args.manifest_path,
+            format: args.format,
+            cache,
+            from: args.from,
+            to: args.to,
+        })
+    }
+
+    pub fn build_commit(&self, fields: &CommitFields) -> Result<Commit<'_>, Error> {
+        fn get_pr_number(message: &str) -> Option<String> {
             // TODO: this can probably be improved
-            let right_of_parens = message.split_once(" (#").ok_or(())?.1;
-            let pr_number_str = right_of_parens.split_once(')').ok_or(())?.0.to_string();
+            let right_of_parens = message.split_once(" (#")?.1;
+            let pr_number_str = right_of_parens.split_once(')')?.0.to_string();
             debug_assert!(pr_number_str.chars().all(|c| c.is_ascii_digit()));
-            Ok(pr_number_str)
+            Some(pr_number_str)
         }
 
-        let node = value.get("node").ok_or(())?;
-        let author = node
-            .get("author")
-            .and_then(|map| map.get("user"))
-            .and_then(|map| map.get("login"))
-            .and_then(Value::as_str)
-            .ok_or(())?
-            .to_string();
+        let author = fields
+            .author
+            .clone()
+            .ok_or(Error::MissingData("commit author"))?;
+        let full_message = &fields.message;
         // we get just the first line
-        let first_line = node
-            .get("message")
-            .and_then(Value::as_str)
-            .ok_or(())?
+        let first_line = full_message
             .lines()
             .next()
-            .expect("commit message can't be empty")
-            .to_string();
-        let pr_number = get_pr_number(&first_line)?;
+            .expect("commit message can't be empty");
+        let pr_number =
+            get_pr_number(first_line).ok_or(Error::MissingData("PR number in commit message"))?;
         let message = {
-            let idx = first_line.find(" (#").ok_or(())?;
+            let idx = first_line
+                .find(" (#")
+                .ok_or(Error::MissingData("PR number in commit message"))?;
             first_line[..idx].trim().to_string()
         };
-        let date: DateTime<_> = node
-            .get("authoredDate")
-            .and_then(Value::as_str)
-            .map(chrono::DateTime::parse_from_rfc3339)
-            .and_then(Result::ok)
-            .ok_or(())?;
+        let (conventional_type, breaking_marker) = match conventional_type(&message) {
+            Some((ty, breaking)) => (Some(ty), breaking),
+            None => (None, false),
+        };
+        let breaking = breaking_marker || full_message.contains("BREAKING CHANGE");
+        let date: ChronoDateTime<_> = chrono::DateTime::parse_from_rfc3339(&fields.authored_date)
+            .map_err(|_| Error::MissingData("valid commit date"))?;
         Ok(Commit {
             config: self,
             author,
             message,
             pr_number,
             date,
+            conventional_type,
+            breaking,
         })
     }
 
-    pub async fn get_date_last_release(&self, crab: &Octocrab) -> Result<String, ()> {
-        let owner = &self.owner;
-        let repo = &self.repo;
-        let query = format!(
-            r#"
-query {{
-  repository(owner:"{owner}", name:"{repo}") {{
-    latestRelease {{
-      tagCommit {{
-        committedDate
-      }}
-    }}
-  }}
-}}
-            "#,
-        );
-        let response_object: Value = crab
-            .graphql(&query)
-            .await
-            .expect("fetching last release failed");
-        response_object.get("data")
-            .and_then(|obj| obj.get("repository"))
-            .and_then(|obj| obj.get("latestRelease"))
-            .and_then(|obj| obj.get("tagCommit"))
-            .and_then(|obj| obj.get("committedDate"))
+    pub async fn get_date_last_release(&self, crab: &Octocrab) -> Result<String, Error> {
+        let variables = last_release::Variables {
+            owner: self.owner.clone(),
+            repo: self.repo.clone(),
+        };
+        let data = run_query::<LastRelease>(crab, variables).await?;
+        data.repository
+            .and_then(|repo| repo.latest_release)
+            .and_then(|release| release.tag_commit)
+            .map(|commit| commit.committed_date)
+            .ok_or(Error::MissingData("latest release date"))
+    }
+
+    /// The commit date of the given tag, e.g. `v1.2.3`.
+    pub async fn get_tag_commit_date(&self, tag: &str, crab: &Octocrab) -> Result<String, Error> {
+        let variables = tag_commit_date::Variables {
+            owner: self.owner.clone(),
+            repo: self.repo.clone(),
+            qualified_name: format!("refs/tags/{tag}"),
+        };
+        let data = run_query::<TagCommitDate>(crab, variables).await?;
+        let target = data
+            .repository
+            .and_then(|repo| repo.git_ref)
+            .and_then(|git_ref| git_ref.target)
+            .ok_or(Error::MissingData("tag commit date"))?;
+        match target {
+            // A lightweight tag's ref points directly at the commit.
+            TagRefTarget::Commit(commit) => Ok(commit.committed_date),
+            // An annotated tag's ref points at a Tag object, which in turn
+            // points at the commit it was made against.
+            TagRefTarget::Tag(tag) => match tag.target {
+                Some(TagTarget::Commit(commit)) => Ok(commit.committed_date),
+                _ => Err(Error::MissingData("tag commit date")),
+            },
+        }
+    }
+
+    /// The start of the changelog range: `--from`'s commit date if given,
+    /// otherwise the date of the latest GitHub release.
+    pub async fn since_date(&self, crab: &Octocrab) -> Result<String, Error> {
+        match &self.from {
+            Some(tag) => self.get_tag_commit_date(tag, crab).await,
+            None => self.get_date_last_release(crab).await,
+        }
+    }
+
+    /// The end of the changelog range: `--to`'s commit date if given,
+    /// otherwise now.
+    pub async fn until_date(&self, crab: &Octocrab) -> Result<ChronoDateTime<FixedOffset>, Error> {
+        match &self.to {
+            Some(tag) => {
+                let date = self.get_tag_commit_date(tag, crab).await?;
+                chrono::DateTime::parse_from_rfc3339(&date)
+                    .map_err(|_| Error::MissingData("valid tag commit date"))
+            }
+            None => Ok(now_as_fixed_offset()),
+        }
+    }
+
+    /// Resolves the version to bump from, either from `--current-version` or by
+    /// reading it out of the manifest pointed to by `--manifest-path`. Returns
+    /// `Ok(None)` if neither flag was given: a next version just isn't
+    /// recommended in that case, which isn't an error.
+    pub fn current_version(&self) -> Result<Option<Version>, Error> {
+        if let Some(version) = &self.current_version {
+            return Version::parse(version)
+                .map(Some)
+                .map_err(|()| Error::Config("invalid --current-version"));
+        }
+        let Some(path) = self.manifest_path.as_deref() else {
+            return Ok(None);
+        };
+        let contents = std::fs::read_to_string(path)
+            .map_err(|_| Error::Config("failed to read --manifest-path file"))?;
+        version_from_manifest(&contents, path)
+            .map(Some)
+            .map_err(|()| Error::Config("failed to find a version in --manifest-path file"))
+    }
+
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+}
+
+/// The changelog's default end-of-range when no `--to` tag is given: now, in UTC.
+fn now_as_fixed_offset() -> ChronoDateTime<FixedOffset> {
+    Utc::now().with_timezone(&FixedOffset::east_opt(0).expect("zero offset is always valid"))
+}
+
+/// The Conventional-Commits type prefix (`feat`, `fix(parser)`, `feat!`, ...)
+/// from the text before `:` on a commit's first line, and whether it marks a
+/// breaking change via a `!` before the `:`.
+fn conventional_type(first_line: &str) -> Option<(String, bool)> {
+    let (prefix, _) = first_line.split_once(':')?;
+    let breaking = prefix.trim_end().ends_with('!');
+    let ty = prefix
+        .trim_end_matches('!')
+        .trim()
+        .split('(')
+        .next()
+        .unwrap_or_default()
+        .trim();
+    if ty.is_empty() || ty.contains(' ') {
+        return None;
+    }
+    Some((ty.to_lowercase(), breaking))
+}
+
+/// Pulls a `version = "x.y.z"` (Cargo.toml) or `"version": "x.y.z"`
+/// (package.json) field out of a manifest's contents.
+fn version_from_manifest(contents: &str, path: &Path) -> Result<Version, ()> {
+    let is_json = path.extension().and_then(std::ffi::OsStr::to_str) == Some("json");
+    let version = if is_json {
+        let value: Value = serde_json::from_str(contents).map_err(|_| ())?;
+        value
+            .get("version")
             .and_then(Value::as_str)
-            .map(Into::into)
-            .ok_or(())
+            .ok_or(())?
+            .to_string()
+    } else {
+        let value: toml::Value = toml::from_str(contents).map_err(|_| ())?;
+        value
+            .get("package")
+            .and_then(|package| package.get("version"))
+            .and_then(toml::Value::as_str)
+            .ok_or(())?
+            .to_string()
+    };
+    Version::parse(&version)
+}
+
+/// A `major.minor.patch` semver version, and the arithmetic to bump it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    /// A bare `()` error is enough here: there's only one way for this to
+    /// fail ("not a `major.minor.patch` string"), and callers just need to
+    /// know whether parsing succeeded.
+    #[allow(clippy::result_unit_err)]
+    pub fn parse(s: &str) -> Result<Self, ()> {
+        let mut parts = s.trim().splitn(3, '.');
+        let major = parts.next().ok_or(())?.parse().map_err(|_| ())?;
+        let minor = parts.next().ok_or(())?.parse().map_err(|_| ())?;
+        let patch = parts.next().ok_or(())?.parse().map_err(|_| ())?;
+        Ok(Version {
+            major,
+            minor,
+            patch,
+        })
+    }
+
+    /// Bumps this version by `level`, resetting lower components to 0.
+    ///
+    /// Per semver's "anything goes before 1.0" convention, a major bump on a
+    /// `0.x` version only bumps the minor component.
+    #[must_use]
+    pub fn bump(self, level: BumpLevel) -> Self {
+        let level = if self.major == 0 && level == BumpLevel::Major {
+            BumpLevel::Minor
+        } else {
+            level
+        };
+        match level {
+            BumpLevel::Major => Version {
+                major: self.major + 1,
+                minor: 0,
+                patch: 0,
+            },
+            BumpLevel::Minor => Version {
+                major: self.major,
+                minor: self.minor + 1,
+                patch: 0,
+            },
+            BumpLevel::Patch => Version {
+                major: self.major,
+                minor: self.minor,
+                patch: self.patch + 1,
+            },
+        }
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
     }
 }
 
+/// How much a single PR should bump the version by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BumpLevel {
+    Patch,
+    Minor,
+    Major,
+}
+
+/// Which category a PR falls into: an index into `Config::categories`, or
+/// `Ignored` if none of its labels matched.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PRKind {
-    Feature,
-    BugFix,
-    Internal,
+    Category(usize),
     Ignored,
 }
 
 pub async fn get_commits<'c>(
     config: &'c Config,
     date_last_release: &str,
+    until: ChronoDateTime<FixedOffset>,
     crab: &Octocrab,
-) -> Result<Vec<Commit<'c>>, ()> {
+) -> Result<Vec<Commit<'c>>, Error> {
     async fn get_100_commits<'c>(
         config: &'c Config,
         date_last_release: &str,
-        until_date: DateTime<FixedOffset>,
+        until_date: ChronoDateTime<FixedOffset>,
+        // Only the first (most recent) page's `until` is "now" (and so
+        // different on every invocation absent `--to`); later pages paginate
+        // on a fixed historical commit date that's already a stable cache
+        // key, so bucketing it too would let unrelated pages collide.
+        bucket_cache_key: bool,
         crab: &Octocrab,
-    ) -> Result<(Vec<Commit<'c>>, DateTime<FixedOffset>), ()> {
-        let owner = &config.owner;
-        let repo = &config.repo;
-        let until_date = &until_date.to_rfc3339();
-        let query = format!(
-            r#"
-    query {{
-      repository(owner:"{owner}", name:"{repo}") {{
-        refs(refPrefix:"refs/heads/", query:"main", last:1) {{
-          edges {{
-            node {{
-              target {{
-                ... on Commit {{
-                  history(since:"{date_last_release}", until: "{until_date}") {{
-                    edges {{
-                      node {{
-                        author {{
-                          user {{
-                            login
-                          }}
-                        }}
-                        message
-                        authoredDate
-                      }}
-                    }}
-                  }}
-                }}
-              }}
-            }}
-          }}
-        }}
-      }}
-    }}
-            "#,
-        );
-        let response_object: Value = crab
-            .graphql(&query)
-            .await
-            .expect("fetching commits failed");
-        let vec = response_object
-            .get("data")
-            .and_then(|obj| obj.get("repository"))
-            .and_then(|obj| obj.get("refs"))
-            .and_then(|obj| obj.get("edges"))
-            .and_then(|obj| obj.get(0))
-            .and_then(|obj| obj.get("node"))
-            .and_then(|obj| obj.get("target"))
-            .and_then(|obj| obj.get("history"))
-            .and_then(|obj| obj.get("edges"))
-            .and_then(Value::as_array)
-            .cloned()
-            .ok_or(())?;
-        let new_date: DateTime<_> = vec
+    ) -> Result<(Vec<Commit<'c>>, ChronoDateTime<FixedOffset>), Error> {
+        let until_date_str = until_date.to_rfc3339();
+        let cache_key_until = if bucket_cache_key {
+            config
+                .cache
+                .bucket(until_date.timestamp().max(0) as u64)
+                .to_string()
+        } else {
+            until_date_str.clone()
+        };
+        let fields = if let Some(cached) = config
+            .cache
+            .get_history_page(date_last_release, &cache_key_until)
+        {
+            cached
+        } else {
+            let variables = get_commits::Variables {
+                owner: config.owner.clone(),
+                repo: config.repo.clone(),
+                since: date_last_release.to_string(),
+                until: until_date_str,
+            };
+            let data = run_query::<GetCommits>(crab, variables).await?;
+            let target = data
+                .repository
+                .and_then(|repo| repo.refs)
+                .and_then(|refs| refs.edges)
+                .unwrap_or_default()
+                .into_iter()
+                .flatten()
+                .next()
+                .and_then(|edge| edge.node)
+                .and_then(|node| node.target)
+                .ok_or(Error::MissingData("default branch ref"))?;
+            let RefTarget::Commit(target_commit) = target else {
+                return Err(Error::MissingData(
+                    "default branch ref pointing at a commit",
+                ));
+            };
+            let history_edges = target_commit.history.edges.unwrap_or_default();
+            let fields: Vec<CommitFields> = history_edges
+                .into_iter()
+                .flatten()
+                .filter_map(|edge| edge.node)
+                .map(|node| CommitFields::from(&node))
+                .collect();
+            config
+                .cache
+                .put_history_page(date_last_release, &cache_key_until, fields.clone());
+            fields
+        };
+        let new_date: ChronoDateTime<_> = fields
             .last()
-            .and_then(|obj| obj.get("node"))
-            .and_then(|obj| obj.get("authoredDate"))
-            .and_then(Value::as_str)
-            .map(chrono::DateTime::parse_from_rfc3339)
-            .and_then(Result::ok)
-            .ok_or(())?
+            .ok_or(Error::MissingData("at least one commit in page"))?
+            .authored_date
+            .parse::<ChronoDateTime<FixedOffset>>()
+            .map_err(|_| Error::MissingData("valid commit date"))?
             .checked_sub_signed(Duration::seconds(1))
             .expect("can't undeflow by subtracting 1 second from a commit's date");
-        let res = vec
+        let res = fields
             .iter()
             // We ignore commits if we can't find the PR number
-            .flat_map(|obj| config.build_commit(obj).ok())
+            .flat_map(|fields| config.build_commit(fields).ok())
             .collect();
         Ok((res, new_date))
     }
     // each query only gets 100 commits, so we need to do it in batches of 100
     // we use the date of the last one we received to paginate
-    let now = Utc::now();
-    let now = now.with_timezone(&FixedOffset::east(0));
-
-    let (mut ret, mut until_date) = get_100_commits(config, date_last_release, now, crab).await?;
+    let (mut ret, mut until_date) =
+        get_100_commits(config, date_last_release, until, config.to.is_none(), crab).await?;
     loop {
-        let res = get_100_commits(config, date_last_release, until_date, crab).await;
+        let res = get_100_commits(config, date_last_release, until_date, false, crab).await;
         match res {
             Ok((new_100, new_until)) => {
                 if new_100.is_empty() {
@@ -251,65 +724,84 @@ impl Ord for PR<'_> {
 
 impl PartialOrd for PR<'_> {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.commit.partial_cmp(&other.commit)
-    }
-}
-
-pub async fn pr_from_commit<'c>(commit: Commit<'c>, crab: &Octocrab) -> Result<PR<'c>, ()> {
-    // TODO: get mapping from config
-    let mapping: HashMap<&str, PRKind> = HashMap::from([
-        ("enhancement", PRKind::Feature),
-        ("bug", PRKind::BugFix),
-        ("Internal", PRKind::Internal),
-    ]);
-    let owner = &commit.config.owner;
-    let repo = &commit.config.repo;
-    let pr_number = &commit.pr_number;
-    let query = format!(
-        r#"
-query {{
-  repository(owner:"{owner}", name:"{repo}") {{
-    pullRequest(number:{pr_number}) {{
-      labels(first: 10) {{
-        edges {{
-          node {{
-            name
-          }}
-        }}
-      }}
-    }}
-  }}
-}}"#,
-    );
-    let response_object: serde_json::Value = crab
-        .graphql(&query)
-        .await
-        .expect("fetching labels failed");
-    let label_array = response_object
-        .get("data")
-        .and_then(|obj| obj.get("repository"))
-        .and_then(|obj| obj.get("pullRequest"))
-        .and_then(|obj| obj.get("labels"))
-        .and_then(|obj| obj.get("edges"))
-        .and_then(Value::as_array)
-        .ok_or(())?;
-    for label in label_array
-        .iter()
-        .flat_map(|object| object.get("node"))
-        .flat_map(|object| object.get("name"))
-        .flat_map(Value::as_str)
-    {
-        if let Some(kind) = mapping.get(label) {
-            return Ok(PR {
-                commit,
-                kind: *kind,
-            });
+        Some(self.cmp(other))
+    }
+}
+
+impl PR<'_> {
+    /// How much this PR should bump the version by: a breaking change is
+    /// always major; a `feat` commit, or a PR routed into a category with
+    /// `minor_bump` set, is minor; everything else is a patch.
+    pub fn bump_level(&self) -> BumpLevel {
+        let feature_category = matches!(
+            self.kind,
+            PRKind::Category(idx) if self.commit.config.categories[idx].minor_bump
+        );
+        if self.commit.breaking {
+            BumpLevel::Major
+        } else if self.commit.conventional_type.as_deref() == Some("feat") || feature_category {
+            BumpLevel::Minor
+        } else {
+            BumpLevel::Patch
         }
     }
-    Ok(PR {
-        commit,
-        kind: PRKind::Ignored,
-    })
+}
+
+/// The highest bump level across all non-ignored PRs, or `None` if there are none.
+pub fn recommend_bump<'a, 'c: 'a>(prs: impl IntoIterator<Item = &'a PR<'c>>) -> Option<BumpLevel> {
+    prs.into_iter()
+        .filter(|pr| pr.kind != PRKind::Ignored)
+        .map(PR::bump_level)
+        .max()
+}
+
+pub async fn pr_from_commit<'c>(commit: Commit<'c>, crab: &Octocrab) -> Result<PR<'c>, Error> {
+    let labels = if let Some(cached) = commit.config.cache.get_labels(&commit.pr_number) {
+        cached
+    } else {
+        let number: i64 = commit
+            .pr_number
+            .parse()
+            .map_err(|_| Error::MissingData("numeric PR number"))?;
+        let variables = pr_labels::Variables {
+            owner: commit.config.owner.clone(),
+            repo: commit.config.repo.clone(),
+            number,
+        };
+        let data = run_query::<PrLabels>(crab, variables).await?;
+        let labels: Vec<String> = data
+            .repository
+            .and_then(|repo| repo.pull_request)
+            .and_then(|pr| pr.labels)
+            .and_then(|labels| labels.edges)
+            .unwrap_or_default()
+            .into_iter()
+            .flatten()
+            .filter_map(|edge| edge.node)
+            .map(|label| label.name)
+            .collect();
+        commit
+            .config
+            .cache
+            .put_labels(&commit.pr_number, labels.clone());
+        labels
+    };
+    let kind = category_for_labels(&commit.config.categories, &labels);
+    Ok(PR { commit, kind })
+}
+
+/// Routes a PR into the category of the first of its GitHub `labels` (in
+/// label order) that matches one of `categories`' `labels` lists, or
+/// `PRKind::Ignored` if none of them do.
+fn category_for_labels(categories: &[Category], labels: &[String]) -> PRKind {
+    labels
+        .iter()
+        .find_map(|label| {
+            categories
+                .iter()
+                .position(|category| category.labels.iter().any(|l| l == label))
+        })
+        .map_or(PRKind::Ignored, PRKind::Category)
 }
 
 pub fn eprint_ignored(ignored: &BinaryHeap<PR>) {
@@ -321,3 +813,340 @@ pub fn eprint_ignored(ignored: &BinaryHeap<PR>) {
         eprintln!("#{number} @{author}: {message}");
     }
 }
+
+/// Renders the categorized PR lists to stdout, in the given `format`.
+///
+/// `categorized` must have one `BinaryHeap` per entry in `config.categories`,
+/// in the same order.
+pub fn render(config: &Config, format: OutputFormat, categorized: Vec<BinaryHeap<PR>>) {
+    match format {
+        OutputFormat::Markdown => render_markdown(config, categorized),
+        OutputFormat::Rss => render_rss(config, categorized),
+        OutputFormat::Json => render_json(config, categorized),
+    }
+}
+
+fn render_markdown(config: &Config, categorized: Vec<BinaryHeap<PR>>) {
+    for (category, prs) in config.categories.iter().zip(categorized) {
+        println!("{}", category.heading);
+        println!();
+        for pr in prs.into_sorted_vec() {
+            println!("- {}", pr.commit);
+        }
+        println!();
+    }
+}
+
+/// Escapes the characters that aren't legal verbatim in RSS text content.
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn render_rss(config: &Config, categorized: Vec<BinaryHeap<PR>>) {
+    let mut all: Vec<PR> = categorized
+        .into_iter()
+        .flat_map(BinaryHeap::into_sorted_vec)
+        .collect();
+    all.sort();
+
+    println!(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    println!(r#"<rss version="2.0">"#);
+    println!("<channel>");
+    println!(
+        "<title>{}/{} changelog</title>",
+        escape_xml(&config.owner),
+        escape_xml(&config.repo)
+    );
+    println!(
+        "<link>https://github.com/{}/{}</link>",
+        config.owner, config.repo
+    );
+    println!("<description>Unreleased changes</description>");
+    for pr in all {
+        let url = pr.commit.pr_url();
+        println!("<item>");
+        println!("<title>{}</title>", escape_xml(&pr.commit.message));
+        println!("<link>{url}</link>");
+        println!("<guid>{url}</guid>");
+        println!(
+            "<description>{}</description>",
+            escape_xml(&pr.commit.author)
+        );
+        println!("<pubDate>{}</pubDate>", pr.commit.date.to_rfc2822());
+        println!("</item>");
+    }
+    println!("</channel>");
+    println!("</rss>");
+}
+
+/// Maps a category's PRs to the JSON array `render_json` nests them under.
+fn prs_to_json(prs: &[PR]) -> Value {
+    Value::Array(
+        prs.iter()
+            .map(|pr| {
+                serde_json::json!({
+                    "message": pr.commit.message,
+                    "author": pr.commit.author,
+                    "url": pr.commit.pr_url(),
+                    "date": pr.commit.date.to_rfc3339(),
+                })
+            })
+            .collect(),
+    )
+}
+
+fn render_json(config: &Config, categorized: Vec<BinaryHeap<PR>>) {
+    let mut map = serde_json::Map::new();
+    for (category, prs) in config.categories.iter().zip(categorized) {
+        map.insert(
+            category.heading.clone(),
+            prs_to_json(&prs.into_sorted_vec()),
+        );
+    }
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&Value::Object(map))
+            .expect("changelog JSON is always serializable")
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_parses_major_minor_patch() {
+        assert_eq!(
+            Version::parse("1.2.3"),
+            Ok(Version {
+                major: 1,
+                minor: 2,
+                patch: 3
+            })
+        );
+    }
+
+    #[test]
+    fn version_parse_rejects_malformed_input() {
+        assert_eq!(Version::parse("not-a-version"), Err(()));
+        assert_eq!(Version::parse("1.2"), Err(()));
+    }
+
+    #[test]
+    fn version_from_manifest_reads_cargo_toml_package_version() {
+        let contents = "[package]\nname = \"crab-log\"\nversion = \"1.2.3\"\n";
+        assert_eq!(
+            version_from_manifest(contents, Path::new("Cargo.toml")),
+            Ok(Version {
+                major: 1,
+                minor: 2,
+                patch: 3
+            })
+        );
+    }
+
+    #[test]
+    fn version_from_manifest_ignores_dependency_table_versions() {
+        let contents =
+            "[dependencies.foo]\nversion = \"9.9.9\"\n\n[package]\nversion = \"1.0.0\"\n";
+        assert_eq!(
+            version_from_manifest(contents, Path::new("Cargo.toml")),
+            Ok(Version {
+                major: 1,
+                minor: 0,
+                patch: 0
+            })
+        );
+    }
+
+    #[test]
+    fn version_from_manifest_reads_package_json_version() {
+        let contents = r#"{"name": "crab-log", "version": "2.0.1"}"#;
+        assert_eq!(
+            version_from_manifest(contents, Path::new("package.json")),
+            Ok(Version {
+                major: 2,
+                minor: 0,
+                patch: 1
+            })
+        );
+    }
+
+    #[test]
+    fn now_as_fixed_offset_is_utc() {
+        assert_eq!(now_as_fixed_offset().offset().local_minus_utc(), 0);
+    }
+
+    fn category(heading: &str, labels: &[&str]) -> Category {
+        Category {
+            heading: heading.to_string(),
+            labels: labels.iter().map(|l| l.to_string()).collect(),
+            minor_bump: false,
+        }
+    }
+
+    #[test]
+    fn category_for_labels_matches_first_matching_label() {
+        let categories = vec![
+            category("Fixes", &["bug"]),
+            category("Features", &["enhancement"]),
+        ];
+        let labels = vec!["enhancement".to_string()];
+        assert_eq!(
+            category_for_labels(&categories, &labels),
+            PRKind::Category(1)
+        );
+    }
+
+    #[test]
+    fn category_for_labels_ignored_without_a_match() {
+        let categories = vec![category("Fixes", &["bug"])];
+        let labels = vec!["documentation".to_string()];
+        assert_eq!(category_for_labels(&categories, &labels), PRKind::Ignored);
+    }
+
+    #[test]
+    fn bump_resets_lower_components() {
+        let v = Version {
+            major: 1,
+            minor: 2,
+            patch: 3,
+        };
+        assert_eq!(
+            v.bump(BumpLevel::Patch),
+            Version {
+                major: 1,
+                minor: 2,
+                patch: 4
+            }
+        );
+        assert_eq!(
+            v.bump(BumpLevel::Minor),
+            Version {
+                major: 1,
+                minor: 3,
+                patch: 0
+            }
+        );
+        assert_eq!(
+            v.bump(BumpLevel::Major),
+            Version {
+                major: 2,
+                minor: 0,
+                patch: 0
+            }
+        );
+    }
+
+    #[test]
+    fn major_bump_on_0x_version_is_only_minor() {
+        let v = Version {
+            major: 0,
+            minor: 4,
+            patch: 1,
+        };
+        assert_eq!(
+            v.bump(BumpLevel::Major),
+            Version {
+                major: 0,
+                minor: 5,
+                patch: 0
+            }
+        );
+    }
+
+    #[test]
+    fn recommend_bump_of_empty_pr_set_is_none() {
+        assert_eq!(recommend_bump(std::iter::empty::<&PR<'static>>()), None);
+    }
+
+    #[test]
+    fn conventional_type_parses_simple_prefix() {
+        assert_eq!(
+            conventional_type("feat: add thing"),
+            Some(("feat".to_string(), false))
+        );
+    }
+
+    #[test]
+    fn conventional_type_parses_scoped_prefix() {
+        assert_eq!(
+            conventional_type("fix(parser): handle empty input"),
+            Some(("fix".to_string(), false))
+        );
+    }
+
+    #[test]
+    fn conventional_type_detects_breaking_marker() {
+        assert_eq!(
+            conventional_type("feat!: drop old API"),
+            Some(("feat".to_string(), true))
+        );
+    }
+
+    #[test]
+    fn conventional_type_none_without_a_colon() {
+        assert_eq!(conventional_type("just a plain commit message"), None);
+    }
+
+    #[test]
+    fn escape_xml_escapes_entities() {
+        assert_eq!(
+            escape_xml("<Title> & \"more\""),
+            "&lt;Title&gt; &amp; \"more\""
+        );
+    }
+
+    fn test_config() -> Config {
+        Config {
+            owner: "boa-dev".to_string(),
+            repo: "crab-log".to_string(),
+            categories: vec![],
+            ignored_authors: vec![],
+            current_version: None,
+            manifest_path: None,
+            format: OutputFormat::Json,
+            cache: Cache::open(
+                None,
+                "boa-dev",
+                "crab-log",
+                std::time::Duration::from_secs(60),
+                true,
+                false,
+            ),
+            from: None,
+            to: None,
+        }
+    }
+
+    #[test]
+    fn prs_to_json_maps_commit_fields() {
+        let config = test_config();
+        let commit = Commit {
+            config: &config,
+            author: "valpackett".to_string(),
+            message: "fix: handle empty input".to_string(),
+            pr_number: "42".to_string(),
+            date: "2024-01-01T00:00:00+00:00"
+                .parse::<ChronoDateTime<FixedOffset>>()
+                .unwrap(),
+            conventional_type: Some("fix".to_string()),
+            breaking: false,
+        };
+        let pr = PR {
+            commit,
+            kind: PRKind::Ignored,
+        };
+        assert_eq!(
+            prs_to_json(&[pr]),
+            serde_json::json!([{
+                "message": "fix: handle empty input",
+                "author": "valpackett",
+                "url": "https://github.com/boa-dev/crab-log/pull/42",
+                "date": "2024-01-01T00:00:00+00:00",
+            }])
+        );
+    }
+}